@@ -5,6 +5,9 @@ use std::{io, fs};
 use std::path::Path;
 
 const APPLE_DOUBLE_MAGIC: u32 = 0x00051607;
+const APPLE_SINGLE_MAGIC: u32 = 0x00051600;
+
+const MAC_BINARY_HEADER_LEN: usize = 128;
 
 #[derive(Debug, FromPrimitive)]
 #[allow(dead_code)]
@@ -71,24 +74,116 @@ pub struct File {
     pub header: Header,
     pub file_info: MacFileInfo,
     pub resource: Vec<u8>,
+    /// The data fork, when it lives inside this very file (AppleSingle). `None` means the
+    /// data fork lives in a sibling file instead, as it does for AppleDouble.
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AppleFormat {
+    Single,
+    Double,
 }
 
-fn is_apple_double(file: &[u8]) -> bool {
+fn apple_format(file: &[u8]) -> Option<AppleFormat> {
     if file.len() < 4 {
+        return None;
+    }
+
+    let magic = u32::from_be_bytes(file[0x0..0x4].try_into().ok()?);
+    match magic {
+        APPLE_DOUBLE_MAGIC => Some(AppleFormat::Double),
+        APPLE_SINGLE_MAGIC => Some(AppleFormat::Single),
+        _ => None,
+    }
+}
+
+pub fn probe<P: AsRef<Path>>(file: &[u8], path: P) -> Option<(Vec<u8>, Vec<u8>, Option<FinderInfo>)> {
+    match apple_format(file) {
+        Some(AppleFormat::Double) => return Some((file.to_vec(), find_data_file(path)?, None)),
+        // AppleSingle carries its own data fork, so there's no sibling file to go looking for;
+        // `unwrap` will pull the data fork out of this same buffer via its `DataFork` entry.
+        Some(AppleFormat::Single) => return Some((file.to_vec(), Vec::new(), None)),
+        None => {}
+    }
+
+    if is_mac_binary(file) {
+        let (data, resource, finder_info) = parse_mac_binary(file)?;
+        return Some((resource, data, Some(finder_info)));
+    }
+
+    Some((find_resource_file(path)?, file.to_vec(), None))
+}
+
+/// CRC-16/XMODEM (poly 0x1021, init 0) as used by the MacBinary II/III header checksum.
+fn crc16_xmodem(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// MacBinary II/III files carry a CRC-16 over the 124-byte header at offset 124; plain
+/// MacBinary I files have no such checksum, so this only recognizes the newer variants.
+fn is_mac_binary(file: &[u8]) -> bool {
+    if file.len() < MAC_BINARY_HEADER_LEN {
+        return false;
+    }
+
+    let name_len = file[1] as usize;
+    if file[0] != 0 || file[74] != 0 || name_len == 0 || name_len > 63 {
         return false;
     }
 
-    let res = file[0x0..0x4].try_into();
+    let expected_crc = u16::from_be_bytes(file[124..126].try_into().unwrap());
+    crc16_xmodem(&file[0..124]) == expected_crc
+}
 
-    res.is_ok() && u32::from_be_bytes(res.unwrap()) == APPLE_DOUBLE_MAGIC
+fn round_up_to_128(len: usize) -> usize {
+    (len + (MAC_BINARY_HEADER_LEN - 1)) & !(MAC_BINARY_HEADER_LEN - 1)
 }
 
-pub fn probe<P: AsRef<Path>>(file: &[u8], path: P) -> Option<(Vec<u8>, Vec<u8>)> {
-    if is_apple_double(&file) {
-        return Some((file.to_vec(), find_data_file(path)?));
+/// Splits a MacBinary II/III file into its data fork, resource fork, and a `FinderInfo`
+/// synthesized from the type/creator/flags carried in the 128-byte header. MacBinary III
+/// additionally carries the `mBIN` signature at offset 102, but the header layout relevant
+/// here is identical between the two, so parsing doesn't need to branch on it.
+fn parse_mac_binary(file: &[u8]) -> Option<(Vec<u8>, Vec<u8>, FinderInfo)> {
+    let type_id = u32::from_be_bytes(file[65..69].try_into().ok()?);
+    let creator_id = u32::from_be_bytes(file[69..73].try_into().ok()?);
+    let finder_flags_hi = file[73];
+
+    let data_len = u32::from_be_bytes(file[83..87].try_into().ok()?) as usize;
+    let resource_len = u32::from_be_bytes(file[87..91].try_into().ok()?) as usize;
+
+    let data_start = MAC_BINARY_HEADER_LEN;
+    let data_end = data_start + data_len;
+    let resource_start = data_start + round_up_to_128(data_len);
+    let resource_end = resource_start + resource_len;
+
+    if file.len() < resource_end {
+        return None;
     }
 
-    Some((find_resource_file(path)?, file.to_vec()))
+    let finder_info = FinderInfo {
+        type_id,
+        creator_id,
+        flags: (finder_flags_hi as u16) << 8,
+        location: (0, 0),
+    };
+
+    Some((
+        file[data_start..data_end].to_vec(),
+        file[resource_start..resource_end].to_vec(),
+        finder_info,
+    ))
 }
 
 fn find_resource_file<P: AsRef<Path>>(path: P) -> Option<Vec<u8>> {
@@ -116,7 +211,9 @@ fn find_resource_file<P: AsRef<Path>>(path: P) -> Option<Vec<u8>> {
 
 fn read_resource<P: AsRef<Path>>(path: P) -> Option<Vec<u8>> {
     if let Ok(file_contents) = fs::read(path) {
-        if is_apple_double(&file_contents) {
+        // AppleSingle is self-contained, not a sidecar format, so a sidecar candidate only
+        // counts as a resource fork here if it's actually AppleDouble.
+        if apple_format(&file_contents) == Some(AppleFormat::Double) {
             return Some(file_contents)
         }
     }
@@ -152,6 +249,7 @@ pub fn unwrap(file: &[u8]) -> BinResult<File> {
         entries.push(cursor.read_be()?);
     }
     let mut resource: Vec<u8> = Vec::new();
+    let mut data: Option<Vec<u8>> = None;
 
     let mut file_info: Option<MacFileInfo> = None;
 
@@ -159,6 +257,7 @@ pub fn unwrap(file: &[u8]) -> BinResult<File> {
         let offset = entry.offset as usize;
         let length = entry.length as usize;
         match FromPrimitive::from_u32(entry.entry_id) {
+            Some(EntryType::DataFork) => data = Some(file[offset..offset + length].to_vec()),
             Some(EntryType::ResourceFork) => resource = file[offset..offset + length].to_vec(),
             Some(EntryType::MacintoshFileInfo) => {
                 cursor.set_position(entry.offset.into());
@@ -168,11 +267,182 @@ pub fn unwrap(file: &[u8]) -> BinResult<File> {
         };
     }
 
+    // AppleDouble's MacintoshFileInfo entry is effectively mandatory on the .rsrc sidecars this
+    // used to be the only caller for, but a well-formed AppleSingle file is free to omit it, so
+    // a missing entry here has to be a parse error rather than a panic.
+    let file_info = file_info.ok_or_else(|| binread::Error::Custom {
+        pos: cursor.position() as usize,
+        err: Box::new("AppleDouble/AppleSingle file is missing a MacintoshFileInfo entry"),
+    })?;
+
     let file = File {
         header,
         resource,
-        file_info: file_info.unwrap(),
+        file_info,
+        data,
     };
 
     Ok(file)
 }
+
+const APPLE_DOUBLE_VERSION: u32 = 0x00020000;
+const APPLE_DOUBLE_HEADER_LEN: u32 = 4 + 4 + 16 + 2;
+const APPLE_DOUBLE_ENTRY_LEN: u32 = 4 + 4 + 4;
+const APPLE_DOUBLE_ENTRY_COUNT: u16 = 2;
+
+/// Serializes a `File`'s resource fork and Finder info back into a standalone AppleDouble
+/// stream, the `unwrap` counterpart. Only the two entries `unwrap` itself understands
+/// (`ResourceFork` and `MacintoshFileInfo`) are written back out; any other entries the
+/// original sidecar carried (comments, icons, ...) are dropped.
+///
+/// This is a plain free function rather than a reader/writer trait pair on `Header`/`Entry`/
+/// `MacFileInfo` (the way decomp-toolkit pairs its own types): `binread`, which those types
+/// derive, has no writer counterpart, and this crate doesn't otherwise depend on a `binwrite`-
+/// style crate. Introducing one just for this one write path isn't worth the added dependency
+/// and derive surface when the format here is two fixed-shape entries and a fixed header —
+/// a free function serializing them by hand is no less clear and needs no new machinery.
+pub fn wrap(file: &File) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&APPLE_DOUBLE_MAGIC.to_be_bytes());
+    out.extend_from_slice(&APPLE_DOUBLE_VERSION.to_be_bytes());
+    out.extend_from_slice(&[0u8; 16]);
+    out.extend_from_slice(&APPLE_DOUBLE_ENTRY_COUNT.to_be_bytes());
+
+    let resource_offset = APPLE_DOUBLE_HEADER_LEN + u32::from(APPLE_DOUBLE_ENTRY_COUNT) * APPLE_DOUBLE_ENTRY_LEN;
+    let resource_len = file.resource.len() as u32;
+    let file_info_offset = resource_offset + resource_len;
+    let file_info_bytes = encode_file_info(&file.file_info);
+
+    write_entry(&mut out, EntryType::ResourceFork as u32, resource_offset, resource_len);
+    write_entry(
+        &mut out,
+        EntryType::MacintoshFileInfo as u32,
+        file_info_offset,
+        file_info_bytes.len() as u32,
+    );
+
+    out.extend_from_slice(&file.resource);
+    out.extend_from_slice(&file_info_bytes);
+
+    out
+}
+
+fn write_entry(out: &mut Vec<u8>, entry_id: u32, offset: u32, length: u32) {
+    out.extend_from_slice(&entry_id.to_be_bytes());
+    out.extend_from_slice(&offset.to_be_bytes());
+    out.extend_from_slice(&length.to_be_bytes());
+}
+
+fn encode_file_info(info: &MacFileInfo) -> Vec<u8> {
+    let mut out = Vec::with_capacity(16 + 16);
+    out.extend_from_slice(&info.finder_info.type_id.to_be_bytes());
+    out.extend_from_slice(&info.finder_info.creator_id.to_be_bytes());
+    out.extend_from_slice(&info.finder_info.flags.to_be_bytes());
+    out.extend_from_slice(&info.finder_info.location.0.to_be_bytes());
+    out.extend_from_slice(&info.finder_info.location.1.to_be_bytes());
+    out.extend_from_slice(&info.extended_info);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_apple_double() -> Vec<u8> {
+        let resource = vec![0xAAu8, 0xBB, 0xCC, 0xDD, 0xEE];
+        let mut file_info = vec![
+            0x41, 0x50, 0x50, 0x4C, // type_id 'APPL'
+            0x3F, 0x3F, 0x3F, 0x3F, // creator_id '????'
+            0x00, 0x01, // flags
+            0x00, 0x00, 0x00, 0x00, // location
+        ];
+        file_info.extend_from_slice(&[0u8; 16]); // extended_info
+
+        let resource_offset = APPLE_DOUBLE_HEADER_LEN + 2 * APPLE_DOUBLE_ENTRY_LEN;
+        let file_info_offset = resource_offset + resource.len() as u32;
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&APPLE_DOUBLE_MAGIC.to_be_bytes());
+        out.extend_from_slice(&APPLE_DOUBLE_VERSION.to_be_bytes());
+        out.extend_from_slice(&[0u8; 16]);
+        out.extend_from_slice(&2u16.to_be_bytes());
+
+        write_entry(&mut out, EntryType::ResourceFork as u32, resource_offset, resource.len() as u32);
+        write_entry(&mut out, EntryType::MacintoshFileInfo as u32, file_info_offset, file_info.len() as u32);
+
+        out.extend_from_slice(&resource);
+        out.extend_from_slice(&file_info);
+        out
+    }
+
+    #[test]
+    fn unwrap_errors_instead_of_panicking_without_file_info() {
+        let resource = vec![0xAAu8, 0xBB];
+        let resource_offset = APPLE_DOUBLE_HEADER_LEN + APPLE_DOUBLE_ENTRY_LEN;
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&APPLE_DOUBLE_MAGIC.to_be_bytes());
+        out.extend_from_slice(&APPLE_DOUBLE_VERSION.to_be_bytes());
+        out.extend_from_slice(&[0u8; 16]);
+        out.extend_from_slice(&1u16.to_be_bytes());
+        write_entry(&mut out, EntryType::ResourceFork as u32, resource_offset, resource.len() as u32);
+        out.extend_from_slice(&resource);
+
+        assert!(unwrap(&out).is_err());
+    }
+
+    #[test]
+    fn wrap_unwrap_round_trip() {
+        let original_bytes = sample_apple_double();
+        let file = unwrap(&original_bytes).expect("valid AppleDouble");
+        assert_eq!(file.resource, vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE]);
+        assert_eq!(file.file_info.finder_info.type_id, 0x4150_504C);
+
+        let rewrapped = wrap(&file);
+        assert_eq!(rewrapped, original_bytes);
+
+        let reparsed = unwrap(&rewrapped).expect("re-parseable AppleDouble");
+        assert_eq!(reparsed.resource, file.resource);
+        assert_eq!(reparsed.file_info.finder_info.type_id, file.file_info.finder_info.type_id);
+        assert_eq!(reparsed.file_info.finder_info.creator_id, file.file_info.finder_info.creator_id);
+    }
+
+    #[test]
+    fn parse_mac_binary_splits_forks_and_finder_info() {
+        let data = b"hello data fork!";
+        let resource = b"RSRC payload";
+
+        let mut header = vec![0u8; MAC_BINARY_HEADER_LEN];
+        header[1] = 5; // filename length
+        header[2..7].copy_from_slice(b"Hello");
+        header[65..69].copy_from_slice(b"TEXT"); // file type
+        header[69..73].copy_from_slice(b"ttxt"); // creator
+        header[73] = 0x80; // finder flags high byte (Inited bit)
+        header[83..87].copy_from_slice(&(data.len() as u32).to_be_bytes());
+        header[87..91].copy_from_slice(&(resource.len() as u32).to_be_bytes());
+
+        let crc = crc16_xmodem(&header[0..124]);
+        header[124..126].copy_from_slice(&crc.to_be_bytes());
+
+        let mut file = header;
+        file.extend_from_slice(data);
+        file.resize(MAC_BINARY_HEADER_LEN + round_up_to_128(data.len()), 0);
+        file.extend_from_slice(resource);
+
+        assert!(is_mac_binary(&file));
+
+        let (parsed_data, parsed_resource, finder_info) = parse_mac_binary(&file).expect("valid MacBinary");
+        assert_eq!(parsed_data, data);
+        assert_eq!(parsed_resource, resource);
+        assert_eq!(finder_info.type_id, u32::from_be_bytes(*b"TEXT"));
+        assert_eq!(finder_info.creator_id, u32::from_be_bytes(*b"ttxt"));
+        assert_eq!(finder_info.flags, 0x8000);
+    }
+
+    #[test]
+    fn read_resource_sidecar_check_rejects_apple_single() {
+        let apple_single = APPLE_SINGLE_MAGIC.to_be_bytes();
+        assert_eq!(apple_format(&apple_single), Some(AppleFormat::Single));
+        assert_ne!(apple_format(&apple_single), Some(AppleFormat::Double));
+    }
+}
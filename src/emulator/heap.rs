@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use crate::common::OSErr;
+
+use super::{EmuUC, helpers::UnicornExtras};
+
+/// Size in bytes of a single master pointer slot.
+const MASTER_POINTER_SIZE: u32 = 4;
+
+const FLAG_LOCKED: u8 = 0x80;
+const FLAG_PURGEABLE: u8 = 0x40;
+const FLAG_RESOURCE: u8 = 0x20;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BlockState {
+    /// Address of the relocatable block this master pointer currently points at, or 0 if the
+    /// handle has been emptied.
+    block: u32,
+    /// Size of the block's contents, 0 while emptied.
+    size: u32,
+    locked: bool,
+    purgeable: bool,
+    resource: bool,
+}
+
+impl BlockState {
+    fn state_byte(self) -> u8 {
+        let mut byte = 0u8;
+        if self.locked {
+            byte |= FLAG_LOCKED;
+        }
+        if self.purgeable {
+            byte |= FLAG_PURGEABLE;
+        }
+        if self.resource {
+            byte |= FLAG_RESOURCE;
+        }
+        byte
+    }
+
+    fn set_state_byte(&mut self, byte: u8) {
+        self.locked = byte & FLAG_LOCKED != 0;
+        self.purgeable = byte & FLAG_PURGEABLE != 0;
+        self.resource = byte & FLAG_RESOURCE != 0;
+    }
+}
+
+/// A fixed, non-relocatable region of the block heap that a sliding block can't move through:
+/// a `NewPtr` allocation, or a locked handle's block.
+#[derive(Debug, Clone, Copy)]
+struct Obstacle {
+    addr: u32,
+    size: u32,
+}
+
+/// An unlocked handle's block, free to slide toward the base of the block heap.
+#[derive(Debug, Clone, Copy)]
+struct Movable {
+    key: u32,
+    addr: u32,
+    size: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PlannedMove {
+    key: u32,
+    old_addr: u32,
+    new_addr: u32,
+    size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompactionPlan {
+    moves: Vec<PlannedMove>,
+    next_free: u32,
+}
+
+/// Computes where each movable block ends up after compaction, without touching memory. Pure
+/// address bookkeeping kept separate from `Heap::compact`'s memory copies so the sliding logic
+/// — obstacles included — can be unit tested without an emulator instance.
+fn plan_compaction(base: u32, obstacles: &[Obstacle], movable: &[Movable]) -> CompactionPlan {
+    enum Entry {
+        Obstacle(Obstacle),
+        Movable(Movable),
+    }
+
+    let mut entries: Vec<Entry> = obstacles.iter().copied().map(Entry::Obstacle).collect();
+    entries.extend(movable.iter().copied().map(Entry::Movable));
+    entries.sort_by_key(|e| match *e {
+        Entry::Obstacle(o) => o.addr,
+        Entry::Movable(m) => m.addr,
+    });
+
+    let mut cursor = base;
+    let mut moves = Vec::new();
+    for entry in entries {
+        match entry {
+            Entry::Obstacle(o) => {
+                cursor = cursor.max(o.addr + o.size);
+            }
+            Entry::Movable(m) => {
+                moves.push(PlannedMove { key: m.key, old_addr: m.addr, new_addr: cursor, size: m.size });
+                cursor += m.size;
+            }
+        }
+    }
+
+    CompactionPlan { moves, next_free: cursor }
+}
+
+/// A crude emulation of the classic Mac OS Memory Manager. The master pointer table and the
+/// block heap (`NewPtr` blocks and the relocatable blocks handles point at) are carved out of
+/// opposite ends of the arena: master pointers grow downward from `limit`, blocks grow upward
+/// from `base`. Keeping them disjoint means `compact`, which only ever rearranges the block
+/// heap, can never mistake a live master pointer cell for free space.
+pub struct Heap {
+    base: u32,
+    next_free: u32,
+    next_master_pointer: u32,
+    ptrs: HashMap<u32, u32>,
+    free_master_pointers: Vec<u32>,
+    blocks: HashMap<u32, BlockState>,
+}
+
+impl Heap {
+    pub fn new(base: u32, limit: u32) -> Self {
+        Heap {
+            base,
+            next_free: base,
+            next_master_pointer: limit,
+            ptrs: HashMap::new(),
+            free_master_pointers: Vec::new(),
+            blocks: HashMap::new(),
+        }
+    }
+
+    fn bump_alloc(&mut self, size: u32) -> Option<u32> {
+        let addr = self.next_free;
+        let end = addr.checked_add(size)?;
+        if end > self.next_master_pointer {
+            return None;
+        }
+        self.next_free = end;
+        Some(addr)
+    }
+
+    fn alloc_master_pointer(&mut self) -> Result<u32, OSErr> {
+        if let Some(mp) = self.free_master_pointers.pop() {
+            return Ok(mp);
+        }
+        let mp = self
+            .next_master_pointer
+            .checked_sub(MASTER_POINTER_SIZE)
+            .ok_or(OSErr::NotEnoughMemory)?;
+        if mp < self.next_free {
+            return Err(OSErr::NotEnoughMemory);
+        }
+        self.next_master_pointer = mp;
+        Ok(mp)
+    }
+
+    fn write_master_pointer(&self, uc: &mut EmuUC, mp: u32, value: u32) -> Result<(), OSErr> {
+        for (i, byte) in value.to_be_bytes().into_iter().enumerate() {
+            uc.write_u8(mp + i as u32, byte)?;
+        }
+        Ok(())
+    }
+
+    pub fn new_ptr(&mut self, _uc: &mut EmuUC, size: u32) -> Result<u32, OSErr> {
+        let addr = self.bump_alloc(size).ok_or(OSErr::NotEnoughMemory)?;
+        self.ptrs.insert(addr, size);
+        Ok(addr)
+    }
+
+    pub fn dispose_ptr(&mut self, _uc: &mut EmuUC, ptr: u32) -> Result<(), OSErr> {
+        self.ptrs.remove(&ptr);
+        Ok(())
+    }
+
+    pub fn get_ptr_size(&self, _uc: &mut EmuUC, ptr: u32) -> Result<u32, OSErr> {
+        Ok(self.ptrs.get(&ptr).copied().unwrap_or(0))
+    }
+
+    pub fn set_ptr_size(&mut self, _uc: &mut EmuUC, ptr: u32, new_size: u32) -> Result<(), OSErr> {
+        self.ptrs.insert(ptr, new_size);
+        Ok(())
+    }
+
+    pub fn new_handle(&mut self, uc: &mut EmuUC, size: u32) -> Result<u32, OSErr> {
+        // Claim the master pointer before touching next_free: alloc_master_pointer can fail on
+        // an exhausted master-pointer region, and bailing out after bump_alloc already advanced
+        // next_free would leak that space with no tracked block to reclaim it.
+        let mp = self.alloc_master_pointer()?;
+
+        let block = match self.bump_alloc(size) {
+            Some(addr) => addr,
+            None => {
+                if let Err(err) = self.compact(uc) {
+                    self.free_master_pointers.push(mp);
+                    return Err(err);
+                }
+                match self.bump_alloc(size) {
+                    Some(addr) => addr,
+                    None => {
+                        self.free_master_pointers.push(mp);
+                        return Err(OSErr::NotEnoughMemory);
+                    }
+                }
+            }
+        };
+
+        self.write_master_pointer(uc, mp, block)?;
+        self.blocks.insert(
+            mp,
+            BlockState {
+                block,
+                size,
+                ..Default::default()
+            },
+        );
+        Ok(mp)
+    }
+
+    pub fn dispose_handle(&mut self, uc: &mut EmuUC, handle: u32) -> Result<(), OSErr> {
+        self.blocks.remove(&handle);
+        self.write_master_pointer(uc, handle, 0)?;
+        self.free_master_pointers.push(handle);
+        Ok(())
+    }
+
+    pub fn get_handle_size(&self, _uc: &mut EmuUC, handle: u32) -> Result<u32, OSErr> {
+        Ok(self.blocks.get(&handle).map(|b| b.size).unwrap_or(0))
+    }
+
+    pub fn set_handle_size(&mut self, uc: &mut EmuUC, handle: u32, new_size: u32) -> Result<bool, OSErr> {
+        let Some(state) = self.blocks.get(&handle).copied() else {
+            return Ok(false);
+        };
+
+        if state.block == 0 {
+            // The handle has been emptied; growing it hands out a brand new block.
+            let block = match self.bump_alloc(new_size) {
+                Some(addr) => addr,
+                None => {
+                    self.compact(uc)?;
+                    match self.bump_alloc(new_size) {
+                        Some(addr) => addr,
+                        None => return Ok(false),
+                    }
+                }
+            };
+            self.write_master_pointer(uc, handle, block)?;
+            if let Some(b) = self.blocks.get_mut(&handle) {
+                b.block = block;
+                b.size = new_size;
+            }
+            return Ok(true);
+        }
+
+        if new_size <= state.size {
+            if let Some(b) = self.blocks.get_mut(&handle) {
+                b.size = new_size;
+            }
+            return Ok(true);
+        }
+
+        // Grow in place when this is the last block in the heap, otherwise fall back to
+        // allocating a fresh block and copying, same as the real Memory Manager relocating a
+        // handle that no longer fits where it sits.
+        let new_block = if state.block + state.size == self.next_free
+            && state.block.checked_add(new_size).is_some_and(|end| end <= self.next_master_pointer)
+        {
+            self.next_free = state.block + new_size;
+            state.block
+        } else {
+            match self.bump_alloc(new_size) {
+                Some(addr) => addr,
+                None => {
+                    self.compact(uc)?;
+                    match self.bump_alloc(new_size) {
+                        Some(addr) => addr,
+                        None => return Ok(false),
+                    }
+                }
+            }
+        };
+
+        // A compaction above may have slid this handle's block, so copy from where it is now.
+        let old_block = self.blocks.get(&handle).map_or(state.block, |b| b.block);
+        if new_block != old_block {
+            for i in 0..state.size {
+                let byte = uc.read_u8(old_block + i)?;
+                uc.write_u8(new_block + i, byte)?;
+            }
+            self.write_master_pointer(uc, handle, new_block)?;
+        }
+
+        if let Some(b) = self.blocks.get_mut(&handle) {
+            b.block = new_block;
+            b.size = new_size;
+        }
+
+        Ok(true)
+    }
+
+    pub fn lock(&mut self, handle: u32, locked: bool) {
+        if let Some(b) = self.blocks.get_mut(&handle) {
+            b.locked = locked;
+        }
+    }
+
+    pub fn get_state(&self, handle: u32) -> u8 {
+        self.blocks.get(&handle).map(|b| b.state_byte()).unwrap_or(0)
+    }
+
+    pub fn set_state(&mut self, handle: u32, state_byte: u8) {
+        if let Some(b) = self.blocks.get_mut(&handle) {
+            b.set_state_byte(state_byte);
+        }
+    }
+
+    /// Slides every unlocked relocatable block toward the base of the block heap, coalescing
+    /// the free space left behind and rewriting each moved block's master pointer. Locked
+    /// handle blocks and `NewPtr` blocks are non-relocatable and act as fixed barriers the
+    /// sliding blocks pile up against instead of sliding through.
+    pub fn compact(&mut self, uc: &mut EmuUC) -> Result<(), OSErr> {
+        let obstacles = self
+            .ptrs
+            .iter()
+            .map(|(&addr, &size)| Obstacle { addr, size })
+            .chain(self.blocks.values().filter(|b| b.block != 0 && b.locked).map(|b| Obstacle {
+                addr: b.block,
+                size: b.size,
+            }))
+            .collect::<Vec<_>>();
+
+        let movable = self
+            .blocks
+            .iter()
+            .filter(|(_, b)| b.block != 0 && !b.locked)
+            .map(|(&handle, b)| Movable { key: handle, addr: b.block, size: b.size })
+            .collect::<Vec<_>>();
+
+        let plan = plan_compaction(self.base, &obstacles, &movable);
+
+        for mv in &plan.moves {
+            if mv.new_addr != mv.old_addr {
+                for i in 0..mv.size {
+                    let byte = uc.read_u8(mv.old_addr + i)?;
+                    uc.write_u8(mv.new_addr + i, byte)?;
+                }
+                self.write_master_pointer(uc, mv.key, mv.new_addr)?;
+                if let Some(b) = self.blocks.get_mut(&mv.key) {
+                    b.block = mv.new_addr;
+                }
+            }
+        }
+
+        self.next_free = plan.next_free;
+        Ok(())
+    }
+
+    /// `MoveHHi` wants a handle's block moved as high in the heap as possible, away from low
+    /// memory. We don't track a separate high/low growth direction, so the closest honest
+    /// approximation is a full compaction. Compaction slides unlocked blocks toward the base of
+    /// the heap, so the handle's block ends up lower or where it was, never higher.
+    pub fn move_high(&mut self, uc: &mut EmuUC, _handle: u32) -> Result<(), OSErr> {
+        self.compact(uc)
+    }
+
+    /// Frees the block a handle points at without giving up the master pointer itself, so a
+    /// later `ReallocateHandle` can hand the same handle a fresh block.
+    pub fn empty_handle(&mut self, uc: &mut EmuUC, handle: u32) -> Result<(), OSErr> {
+        if let Some(b) = self.blocks.get_mut(&handle) {
+            b.block = 0;
+            b.size = 0;
+        }
+        self.write_master_pointer(uc, handle, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compaction_slides_handles_down_and_coalesces_gaps() {
+        // Two handles with a gap between them (A disposed, leaving a hole) and nothing else
+        // in the way: B should slide all the way down to base and the gap should close.
+        let movable = vec![Movable { key: 2, addr: 50, size: 10 }];
+        let plan = plan_compaction(0, &[], &movable);
+
+        assert_eq!(plan.moves, vec![PlannedMove { key: 2, old_addr: 50, new_addr: 0, size: 10 }]);
+        assert_eq!(plan.next_free, 10);
+    }
+
+    #[test]
+    fn compaction_treats_new_ptr_blocks_as_obstacles() {
+        // A NewPtr block sits at 10..15, interleaved between base and a handle at 20..25.
+        // The handle must stop at 15, never slide through (or past) the NewPtr block, and
+        // next_free must land after it, not before.
+        let obstacles = vec![Obstacle { addr: 10, size: 5 }];
+        let movable = vec![Movable { key: 1, addr: 20, size: 5 }];
+
+        let plan = plan_compaction(0, &obstacles, &movable);
+
+        assert_eq!(plan.moves, vec![PlannedMove { key: 1, old_addr: 20, new_addr: 15, size: 5 }]);
+        assert_eq!(plan.next_free, 20);
+    }
+
+    #[test]
+    fn compaction_leaves_locked_handles_in_place_as_obstacles() {
+        // A locked handle's block is itself an obstacle: unlocked blocks pile up against it
+        // rather than overwriting it, and next_free must land past it.
+        let obstacles = vec![Obstacle { addr: 5, size: 10 }];
+        let movable = vec![Movable { key: 1, addr: 30, size: 8 }];
+
+        let plan = plan_compaction(0, &obstacles, &movable);
+
+        assert_eq!(plan.moves, vec![PlannedMove { key: 1, old_addr: 30, new_addr: 15, size: 8 }]);
+        assert_eq!(plan.next_free, 23);
+    }
+
+    #[test]
+    fn compaction_is_a_no_op_when_already_packed() {
+        let movable = vec![
+            Movable { key: 1, addr: 0, size: 10 },
+            Movable { key: 2, addr: 10, size: 5 },
+        ];
+
+        let plan = plan_compaction(0, &[], &movable);
+
+        assert_eq!(
+            plan.moves,
+            vec![
+                PlannedMove { key: 1, old_addr: 0, new_addr: 0, size: 10 },
+                PlannedMove { key: 2, old_addr: 10, new_addr: 10, size: 5 },
+            ]
+        );
+        assert_eq!(plan.next_free, 15);
+    }
+}
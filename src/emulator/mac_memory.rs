@@ -2,10 +2,6 @@ use crate::common::OSErr;
 
 use super::{EmuState, EmuUC, FuncResult, helpers::{ArgReader, UnicornExtras}};
 
-fn stub_return_void(_uc: &mut EmuUC, _state: &mut EmuState, _reader: &mut ArgReader) -> FuncResult {
-	Ok(None)
-}
-
 fn new_handle(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
 	let size: u32 = reader.read1(uc)?;
 	let handle = state.heap.new_handle(uc, size)?;
@@ -69,23 +65,204 @@ fn block_move_data(uc: &mut EmuUC, _state: &mut EmuState, reader: &mut ArgReader
 	Ok(None)
 }
 
-fn h_get_state(_uc: &mut EmuUC, _state: &mut EmuState, _reader: &mut ArgReader) -> FuncResult {
-	// We don't implement this
-	Ok(Some(0))
+fn h_lock(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let handle: u32 = reader.read1(uc)?;
+	state.heap.lock(handle, true);
+	Ok(None)
+}
+
+fn h_unlock(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let handle: u32 = reader.read1(uc)?;
+	state.heap.lock(handle, false);
+	Ok(None)
+}
+
+fn h_lock_hi(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	// We have no separate high-memory segment to pin against, so HLockHi just locks.
+	let handle: u32 = reader.read1(uc)?;
+	state.heap.lock(handle, true);
+	Ok(None)
+}
+
+fn move_h_hi(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let handle: u32 = reader.read1(uc)?;
+	state.heap.move_high(uc, handle)?;
+	Ok(None)
+}
+
+fn h_get_state(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let handle: u32 = reader.read1(uc)?;
+	Ok(Some(state.heap.get_state(handle) as u32))
+}
+
+fn h_set_state(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let (handle, flags): (u32, u32) = reader.read2(uc)?;
+	state.heap.set_state(handle, flags as u8);
+	Ok(None)
+}
+
+/// Whether new bytes land after a handle's existing contents or overwrite it from the start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HandWriteMode {
+	/// `PtrAndHand`/`HandAndHand`: grow the block and write after what's already there.
+	Append,
+	/// `PtrToXHand`: resize to exactly the incoming size and write from the very start.
+	Replace,
+}
+
+/// Computes the block's new total size and the offset new bytes get written at, without
+/// touching the heap or memory. Kept separate from the traps below so the two write modes —
+/// easy to conflate, as `PtrToXHand` briefly was with `PtrAndHand` — can be unit tested
+/// directly.
+fn hand_write_layout(mode: HandWriteMode, current_size: u32, incoming_size: u32) -> (u32, u32) {
+	match mode {
+		HandWriteMode::Append => (current_size + incoming_size, current_size),
+		HandWriteMode::Replace => (incoming_size, 0),
+	}
+}
+
+/// Maps a heap resize outcome to the trap result code returned by the shims below that resize
+/// an existing handle (`PtrAndHand`, `HandAndHand`, `PtrToXHand`, `ReallocateHandle`).
+fn resize_result_code(resized: bool) -> u32 {
+	if resized {
+		0
+	} else {
+		OSErr::NotEnoughMemory.to_u32()
+	}
 }
 
 fn ptr_and_hand(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
 	let (ptr, handle, size): (u32, u32, u32) = reader.read3(uc)?;
 
 	let current_size = state.heap.get_handle_size(uc, handle)?;
-	if state.heap.set_handle_size(uc, handle, current_size + size)? {
-		let dest = uc.read_u32(handle)? + current_size;
+	let (new_size, offset) = hand_write_layout(HandWriteMode::Append, current_size, size);
+	let resized = state.heap.set_handle_size(uc, handle, new_size)?;
+	if resized {
+		let dest = uc.read_u32(handle)? + offset;
 		for i in 0..size {
 			uc.write_u8(dest + i, uc.read_u8(ptr + i)?)?;
 		}
-		Ok(Some(0))
-	} else {
-		Ok(Some(OSErr::NotEnoughMemory.to_u32()))
+	}
+	Ok(Some(resize_result_code(resized)))
+}
+
+fn hand_to_hand(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let handle_ptr: u32 = reader.read1(uc)?;
+	let handle = uc.read_u32(handle_ptr)?;
+	let size = state.heap.get_handle_size(uc, handle)?;
+
+	let new_handle = match state.heap.new_handle(uc, size) {
+		Ok(new_handle) => new_handle,
+		Err(err) => return Ok(Some(err.to_u32())),
+	};
+
+	let src = uc.read_u32(handle)?;
+	let dest = uc.read_u32(new_handle)?;
+	for i in 0..size {
+		uc.write_u8(dest + i, uc.read_u8(src + i)?)?;
+	}
+
+	state.heap.dispose_handle(uc, handle)?;
+	uc.write_u32(handle_ptr, new_handle)?;
+
+	Ok(Some(0))
+}
+
+fn hand_and_hand(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let (src_handle, dest_handle): (u32, u32) = reader.read2(uc)?;
+
+	let src_size = state.heap.get_handle_size(uc, src_handle)?;
+	let dest_size = state.heap.get_handle_size(uc, dest_handle)?;
+	let (new_size, offset) = hand_write_layout(HandWriteMode::Append, dest_size, src_size);
+
+	let resized = state.heap.set_handle_size(uc, dest_handle, new_size)?;
+	if resized {
+		let src = uc.read_u32(src_handle)?;
+		let dest = uc.read_u32(dest_handle)? + offset;
+		for i in 0..src_size {
+			uc.write_u8(dest + i, uc.read_u8(src + i)?)?;
+		}
+	}
+
+	Ok(Some(resize_result_code(resized)))
+}
+
+fn ptr_to_hand(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let (src, handle_ptr, size): (u32, u32, u32) = reader.read3(uc)?;
+
+	let handle = match state.heap.new_handle(uc, size) {
+		Ok(handle) => handle,
+		Err(err) => return Ok(Some(err.to_u32())),
+	};
+
+	let dest = uc.read_u32(handle)?;
+	for i in 0..size {
+		uc.write_u8(dest + i, uc.read_u8(src + i)?)?;
+	}
+
+	uc.write_u32(handle_ptr, handle)?;
+	Ok(Some(0))
+}
+
+fn ptr_to_x_hand(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let (src, handle, size): (u32, u32, u32) = reader.read3(uc)?;
+
+	let (new_size, offset) = hand_write_layout(HandWriteMode::Replace, 0, size);
+	let resized = state.heap.set_handle_size(uc, handle, new_size)?;
+	if resized {
+		let dest = uc.read_u32(handle)? + offset;
+		for i in 0..size {
+			uc.write_u8(dest + i, uc.read_u8(src + i)?)?;
+		}
+	}
+	Ok(Some(resize_result_code(resized)))
+}
+
+fn reallocate_handle(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let (handle, new_size): (u32, u32) = reader.read2(uc)?;
+	let resized = state.heap.set_handle_size(uc, handle, new_size)?;
+	Ok(Some(resize_result_code(resized)))
+}
+
+fn empty_handle(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let handle: u32 = reader.read1(uc)?;
+	state.heap.empty_handle(uc, handle)?;
+	Ok(None)
+}
+
+fn new_empty_handle(uc: &mut EmuUC, state: &mut EmuState, reader: &mut ArgReader) -> FuncResult {
+	let handle = state.heap.new_handle(uc, 0)?;
+	state.heap.empty_handle(uc, handle)?;
+	Ok(Some(handle))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn append_grows_the_block_and_writes_after_existing_contents() {
+		// PtrAndHand/HandAndHand: a 10-byte handle receiving 5 more bytes ends up 15 bytes,
+		// with the new bytes starting right after the original 10.
+		let (new_size, offset) = hand_write_layout(HandWriteMode::Append, 10, 5);
+		assert_eq!(new_size, 15);
+		assert_eq!(offset, 10);
+	}
+
+	#[test]
+	fn replace_resizes_to_the_incoming_size_and_writes_from_the_start() {
+		// PtrToXHand: regardless of what the handle held before, it's resized to exactly the
+		// incoming size and overwritten starting at offset 0 — not appended after the old
+		// contents the way PtrAndHand briefly aliased it to.
+		let (new_size, offset) = hand_write_layout(HandWriteMode::Replace, 10, 5);
+		assert_eq!(new_size, 5);
+		assert_eq!(offset, 0);
+	}
+
+	#[test]
+	fn resize_result_code_reports_not_enough_memory_on_failure() {
+		assert_eq!(resize_result_code(true), 0);
+		assert_eq!(resize_result_code(false), OSErr::NotEnoughMemory.to_u32());
 	}
 }
 
@@ -94,10 +271,10 @@ pub(super) fn install_shims(state: &mut EmuState) {
 	state.install_shim_function("NewHandleClear", new_handle);
 	state.install_shim_function("NewPtr", new_ptr);
 	state.install_shim_function("NewPtrClear", new_ptr);
-	state.install_shim_function("HLock", stub_return_void);
-	state.install_shim_function("HUnlock", stub_return_void);
-	state.install_shim_function("HLockHi", stub_return_void);
-	state.install_shim_function("MoveHHi", stub_return_void);
+	state.install_shim_function("HLock", h_lock);
+	state.install_shim_function("HUnlock", h_unlock);
+	state.install_shim_function("HLockHi", h_lock_hi);
+	state.install_shim_function("MoveHHi", move_h_hi);
 	state.install_shim_function("DisposePtr", dispose_ptr);
 	state.install_shim_function("GetPtrSize", get_ptr_size);
 	state.install_shim_function("SetPtrSize", set_ptr_size);
@@ -106,6 +283,13 @@ pub(super) fn install_shims(state: &mut EmuState) {
 	state.install_shim_function("SetHandleSize", set_handle_size);
 	state.install_shim_function("BlockMoveData", block_move_data);
 	state.install_shim_function("HGetState", h_get_state);
-	state.install_shim_function("HSetState", stub_return_void);
+	state.install_shim_function("HSetState", h_set_state);
 	state.install_shim_function("PtrAndHand", ptr_and_hand);
+	state.install_shim_function("HandToHand", hand_to_hand);
+	state.install_shim_function("HandAndHand", hand_and_hand);
+	state.install_shim_function("PtrToHand", ptr_to_hand);
+	state.install_shim_function("PtrToXHand", ptr_to_x_hand);
+	state.install_shim_function("ReallocateHandle", reallocate_handle);
+	state.install_shim_function("EmptyHandle", empty_handle);
+	state.install_shim_function("NewEmptyHandle", new_empty_handle);
 }